@@ -0,0 +1,126 @@
+//! Turns the per-thread logs written by the `observe_locks` feature
+//! into a suggested [`define_rank!`](crate::define_rank) ranking.
+//!
+//! This is the library half of the `ordered_mutex_analyze` binary;
+//! see `src/bin/ordered_mutex_analyze.rs` for the command-line interface.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// The lock-nesting pairs observed across every thread, merged into a
+/// single graph: for each observed rank, the set of ranks seen
+/// acquired while it was the youngest lock held, plus a representative
+/// label for each rank.
+pub struct ObservedGraph {
+    labels: BTreeMap<u32, String>,
+    edges: BTreeMap<u32, BTreeSet<u32>>,
+}
+
+impl ObservedGraph {
+    /// Read every `*.log` file in `dir` (as written by one thread of
+    /// an `observe_locks`-enabled program) and merge them into a
+    /// single graph.
+    pub fn read_dir(dir: impl AsRef<Path>) -> std::io::Result<Self> {
+        let mut graph = ObservedGraph {
+            labels: BTreeMap::new(),
+            edges: BTreeMap::new(),
+        };
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("log") {
+                continue;
+            }
+            for line in std::fs::read_to_string(path)?.lines() {
+                if let Some((outer_label, outer_rank, inner_label, inner_rank)) = parse_line(line) {
+                    graph.labels.entry(outer_rank).or_insert(outer_label);
+                    graph.labels.entry(inner_rank).or_insert(inner_label);
+                    graph.edges.entry(outer_rank).or_default().insert(inner_rank);
+                }
+            }
+        }
+        Ok(graph)
+    }
+
+    /// If the observed graph contains a cycle, return the chain of
+    /// ranks that proves it (the starting rank repeated at the end).
+    pub fn find_cycle(&self) -> Option<Vec<u32>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            Unvisited,
+            InProgress,
+            Done,
+        }
+
+        fn visit(
+            rank: u32,
+            edges: &BTreeMap<u32, BTreeSet<u32>>,
+            marks: &mut BTreeMap<u32, Mark>,
+            path: &mut Vec<u32>,
+        ) -> Option<Vec<u32>> {
+            match marks[&rank] {
+                Mark::Done => return None,
+                Mark::InProgress => {
+                    let start = path.iter().position(|&r| r == rank).expect("in-progress rank must be on the path");
+                    let mut cycle = path[start..].to_vec();
+                    cycle.push(rank);
+                    return Some(cycle);
+                }
+                Mark::Unvisited => {}
+            }
+
+            marks.insert(rank, Mark::InProgress);
+            path.push(rank);
+            for &next in edges.get(&rank).into_iter().flatten() {
+                if let Some(cycle) = visit(next, edges, marks, path) {
+                    return Some(cycle);
+                }
+            }
+            path.pop();
+            marks.insert(rank, Mark::Done);
+            None
+        }
+
+        let mut marks: BTreeMap<u32, Mark> = self.labels.keys().map(|&rank| (rank, Mark::Unvisited)).collect();
+        for rank in self.labels.keys().copied() {
+            let mut path = Vec::new();
+            if let Some(cycle) = visit(rank, &self.edges, &mut marks, &mut path) {
+                return Some(cycle);
+            }
+        }
+        None
+    }
+
+    /// Render this graph as `define_rank!` follower-list syntax, one
+    /// line per observed rank, as a starting point for the ranking you
+    /// actually declare.
+    pub fn suggest_ranking(&self) -> String {
+        let mut out = String::new();
+        for (rank, label) in &self.labels {
+            let followers = self
+                .edges
+                .get(rank)
+                .into_iter()
+                .flatten()
+                .map(|follower_rank| self.labels[follower_rank].as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            out.push_str(&format!("    {label} => {{ {followers} }}, // observed rank {rank}\n"));
+        }
+        out
+    }
+
+    /// The label recorded for `rank`, or `"?"` if this graph never
+    /// observed it.
+    pub fn label(&self, rank: u32) -> &str {
+        self.labels.get(&rank).map(String::as_str).unwrap_or("?")
+    }
+}
+
+fn parse_line(line: &str) -> Option<(String, u32, String, u32)> {
+    let mut fields = line.split('\t');
+    let outer_label = fields.next()?.to_string();
+    let outer_rank = fields.next()?.parse().ok()?;
+    let inner_label = fields.next()?.to_string();
+    let inner_rank = fields.next()?.parse().ok()?;
+    Some((outer_label, outer_rank, inner_label, inner_rank))
+}
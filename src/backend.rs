@@ -0,0 +1,203 @@
+//! Abstracts over the lock implementation that [`Mutex`](crate::Mutex)
+//! and [`RwLock`](crate::RwLock) wrap, so the rank-checking layer in
+//! `lib.rs` doesn't need to know or care whether it's sitting on top
+//! of `std::sync` or `parking_lot`.
+//!
+//! `std::sync`'s locks poison themselves when a guard is dropped
+//! during a panic, so acquiring them returns a `Result`.
+//! `parking_lot`'s locks don't poison, so acquiring them returns the
+//! guard directly. [`Poison`] makes that difference visible at the
+//! type level instead of papering over it with a `Result` nobody ever
+//! returns `Err` from.
+
+/// How a backend reports whether a lock was poisoned.
+pub trait Poison {
+    /// The shape `Mutex::lock`/`RwLock::read`/`RwLock::write` return:
+    /// `std::sync::LockResult<G>` for a poisoning backend, or plain
+    /// `G` for one that never poisons.
+    type Wrapped<'a, G>
+    where
+        G: 'a;
+
+    fn wrap<'a, G: 'a>(guard: G, poisoned: bool) -> Self::Wrapped<'a, G>;
+}
+
+/// Poison behavior for backends built on `std::sync`.
+pub enum StdPoison {}
+
+impl Poison for StdPoison {
+    type Wrapped<'a, G>
+        = std::sync::LockResult<G>
+    where
+        G: 'a;
+
+    fn wrap<'a, G: 'a>(guard: G, poisoned: bool) -> Self::Wrapped<'a, G> {
+        if poisoned {
+            Err(std::sync::PoisonError::new(guard))
+        } else {
+            Ok(guard)
+        }
+    }
+}
+
+/// Poison behavior for backends that never poison, such as
+/// `parking_lot`.
+pub enum NoPoison {}
+
+impl Poison for NoPoison {
+    type Wrapped<'a, G>
+        = G
+    where
+        G: 'a;
+
+    fn wrap<'a, G: 'a>(guard: G, _poisoned: bool) -> Self::Wrapped<'a, G> {
+        guard
+    }
+}
+
+/// A mutex implementation that [`Mutex`](crate::Mutex) can wrap.
+pub trait MutexBackend<T>: Sized {
+    type Guard<'a>: std::ops::Deref<Target = T> + std::ops::DerefMut<Target = T>
+    where
+        T: 'a,
+        Self: 'a;
+
+    type Poison: Poison;
+
+    fn new(value: T) -> Self;
+
+    /// Acquire the lock, returning the raw guard along with whether
+    /// this acquisition observed the lock poisoned.
+    fn raw_lock(&self) -> (Self::Guard<'_>, bool);
+}
+
+/// An `RwLock` implementation that [`RwLock`](crate::RwLock) can wrap.
+pub trait RwLockBackend<T>: Sized {
+    type ReadGuard<'a>: std::ops::Deref<Target = T>
+    where
+        T: 'a,
+        Self: 'a;
+
+    type WriteGuard<'a>: std::ops::Deref<Target = T> + std::ops::DerefMut<Target = T>
+    where
+        T: 'a,
+        Self: 'a;
+
+    type Poison: Poison;
+
+    fn new(value: T) -> Self;
+    fn raw_read(&self) -> (Self::ReadGuard<'_>, bool);
+    fn raw_write(&self) -> (Self::WriteGuard<'_>, bool);
+}
+
+/// The default backend: the Rust standard library's poisoning locks.
+pub struct StdBackend<T>(std::sync::Mutex<T>);
+
+impl<T> MutexBackend<T> for StdBackend<T> {
+    type Guard<'a>
+        = std::sync::MutexGuard<'a, T>
+    where
+        T: 'a;
+
+    type Poison = StdPoison;
+
+    fn new(value: T) -> Self {
+        StdBackend(std::sync::Mutex::new(value))
+    }
+
+    fn raw_lock(&self) -> (Self::Guard<'_>, bool) {
+        match self.0.lock() {
+            Ok(guard) => (guard, false),
+            Err(poison_error) => (poison_error.into_inner(), true),
+        }
+    }
+}
+
+/// The default `RwLock` backend: the standard library's poisoning
+/// `RwLock`.
+pub struct StdRwLockBackend<T>(std::sync::RwLock<T>);
+
+impl<T> RwLockBackend<T> for StdRwLockBackend<T> {
+    type ReadGuard<'a>
+        = std::sync::RwLockReadGuard<'a, T>
+    where
+        T: 'a;
+
+    type WriteGuard<'a>
+        = std::sync::RwLockWriteGuard<'a, T>
+    where
+        T: 'a;
+
+    type Poison = StdPoison;
+
+    fn new(value: T) -> Self {
+        StdRwLockBackend(std::sync::RwLock::new(value))
+    }
+
+    fn raw_read(&self) -> (Self::ReadGuard<'_>, bool) {
+        match self.0.read() {
+            Ok(guard) => (guard, false),
+            Err(poison_error) => (poison_error.into_inner(), true),
+        }
+    }
+
+    fn raw_write(&self) -> (Self::WriteGuard<'_>, bool) {
+        match self.0.write() {
+            Ok(guard) => (guard, false),
+            Err(poison_error) => (poison_error.into_inner(), true),
+        }
+    }
+}
+
+/// Wraps [`parking_lot::Mutex`], which never poisons.
+#[cfg(feature = "parking_lot")]
+pub struct ParkingLotBackend<T>(parking_lot::Mutex<T>);
+
+#[cfg(feature = "parking_lot")]
+impl<T> MutexBackend<T> for ParkingLotBackend<T> {
+    type Guard<'a>
+        = parking_lot::MutexGuard<'a, T>
+    where
+        T: 'a;
+
+    type Poison = NoPoison;
+
+    fn new(value: T) -> Self {
+        ParkingLotBackend(parking_lot::Mutex::new(value))
+    }
+
+    fn raw_lock(&self) -> (Self::Guard<'_>, bool) {
+        (self.0.lock(), false)
+    }
+}
+
+/// Wraps [`parking_lot::RwLock`], which never poisons.
+#[cfg(feature = "parking_lot")]
+pub struct ParkingLotRwLockBackend<T>(parking_lot::RwLock<T>);
+
+#[cfg(feature = "parking_lot")]
+impl<T> RwLockBackend<T> for ParkingLotRwLockBackend<T> {
+    type ReadGuard<'a>
+        = parking_lot::RwLockReadGuard<'a, T>
+    where
+        T: 'a;
+
+    type WriteGuard<'a>
+        = parking_lot::RwLockWriteGuard<'a, T>
+    where
+        T: 'a;
+
+    type Poison = NoPoison;
+
+    fn new(value: T) -> Self {
+        ParkingLotRwLockBackend(parking_lot::RwLock::new(value))
+    }
+
+    fn raw_read(&self) -> (Self::ReadGuard<'_>, bool) {
+        (self.0.read(), false)
+    }
+
+    fn raw_write(&self) -> (Self::WriteGuard<'_>, bool) {
+        (self.0.write(), false)
+    }
+}
@@ -0,0 +1,34 @@
+//! Merge the per-thread logs from an `observe_locks` run and print a
+//! suggested ranking.
+//!
+//! Usage: `ordered_mutex_analyze <observation-dir>`
+
+#[cfg(feature = "observe_locks")]
+fn main() {
+    let dir = std::env::args().nth(1).unwrap_or_else(|| {
+        eprintln!("usage: ordered_mutex_analyze <observation-dir>");
+        std::process::exit(1);
+    });
+
+    let graph = ordered_mutex::analyze::ObservedGraph::read_dir(&dir).unwrap_or_else(|err| {
+        eprintln!("failed to read observation logs in {dir}: {err}");
+        std::process::exit(1);
+    });
+
+    if let Some(cycle) = graph.find_cycle() {
+        let chain: Vec<&str> = cycle.iter().map(|&rank| graph.label(rank)).collect();
+        eprintln!("observed acquisitions are not acyclic:");
+        eprintln!("  {}", chain.join(" -> "));
+        std::process::exit(1);
+    }
+
+    println!("enum ObservedRank {{");
+    print!("{}", graph.suggest_ranking());
+    println!("}}");
+}
+
+#[cfg(not(feature = "observe_locks"))]
+fn main() {
+    eprintln!("ordered_mutex_analyze requires the `observe_locks` feature");
+    std::process::exit(1);
+}
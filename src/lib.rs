@@ -15,17 +15,26 @@
 //! locks, there must be a directed cycle of threads, each of which is
 //! holding a lock that the next thread in the cycle is waiting for.
 //! Thus, one simple and sufficient way to prevent deadlocks is to
-//! impose a partial order, or "ranking", on all the program's locks,
-//! and forbid threads from acquiring any lock unless it outranks the
-//! locks it already holds. This prevents any such cycles from
-//! forming.
-//!
-//! This crate provides wrappers for `Mutex` and `RwLock` that track
-//! the highest rank of lock that each thread currently holds, and
+//! impose an acyclic ranking on all the program's locks, and forbid a
+//! thread from acquiring a lock unless the youngest lock it already
+//! holds lists the new lock's rank among its *followers* — the ranks
+//! that are allowed to come next. As long as the graph of rank-to-
+//! follower edges has no cycles, no thread can ever be made to wait
+//! for a lock that (transitively) depends on one it's already
+//! holding, so this prevents deadlocks of this kind from forming.
+//!
+//! Unlike a single global numeric order, a follower graph lets
+//! unrelated subsystems be ranked independently: two ranks that never
+//! nest inside one another simply don't mention each other as
+//! followers, instead of having to be placed somewhere in a single
+//! total sequence.
+//!
+//! This crate provides wrappers for `Mutex` and `RwLock` that track,
+//! for each thread, the stack of lock ranks it currently holds, and
 //! panic if a thread violates the order. You specify the ranking, in
-//! the form of an enum that implements [`PartialOrd`], [`Clone`], and
-//! [`Into<u32>`]. You indicate the rank of each lock when you create
-//! it.
+//! the form of an enum that implements [`Clone`] and [`Into<u32>`],
+//! and declares each variant's followers. You indicate
+//! the rank of each lock when you create it.
 //!
 //! Note that this analysis is strictly thread-local, evaluating each
 //! thread's behavior in isolation. It does not depend on any deadlock
@@ -35,9 +44,9 @@
 //!
 //! # How to use this crate
 //!
-//! 1)  Choose a ranking in which the locks in your code must be acquired: a
-//!     thread may only acquire a lock whose rank is higher than any other lock
-//!     it is already holding. Use this crate's `define_rank!` macro to
+//! 1)  Choose a ranking in which the locks in your code must be acquired: for
+//!     each rank, the *followers* it may be followed by while it's the
+//!     youngest lock a thread holds. Use this crate's `define_rank!` macro to
 //!     define an `enum` representing that ranking:
 //!
 //!         ordered_mutex::define_rank! {
@@ -46,19 +55,28 @@
 //!
 //!             /// Order in which GPU locks must be acquired.
 //!             #[repr(u32)]
-//!             #[derive(Clone, PartialOrd, PartialEq)]
+//!             #[derive(Clone, PartialEq)]
 //!             enum GPULockRank {
-//!                 DeviceTracker,
-//!                 BufferMapState,
+//!                 DeviceTracker => { BufferMapState },
+//!                 BufferMapState => {},
 //!             }
 //!         }
 //!
 //!     This defines the `GPULockRank` enum, declares a thread-local
 //!     variable named `GPU_RANK`, and implements this crate's
-//!     [`Rank`] trait for `GPULockRank`.
+//!     [`Rank`] trait for `GPULockRank`, including a `followers`
+//!     method built from the `=> { ... }` lists above. Here, a thread
+//!     holding `DeviceTracker` may go on to acquire `BufferMapState`,
+//!     but one holding `BufferMapState` may not acquire anything else
+//!     without releasing it first.
+//!
+//!     Every variant must list its followers, even if the list is
+//!     empty. The first time a thread locks a lock of this rank type,
+//!     this crate walks the follower graph and panics if it finds a
+//!     cycle, since a cyclic ranking can't prevent deadlocks.
 //!
 //!     Note that the rank enum must implement the standard library's
-//!     [`Clone`] and [`PartialOrd`] traits.
+//!     [`Clone`] trait.
 //!
 //!     Further, to simplify implementation, the rank enum must
 //!     implement `Into<u32>`, and variants must have values less than
@@ -73,8 +91,8 @@
 //!
 //!         # ordered_mutex::define_rank! {
 //!         #     static GPU_RANK;
-//!         #     #[derive(Clone, PartialOrd, PartialEq)]
-//!         #     enum GPULockRank { Nothing, DeviceTracker, BufferMapState, }
+//!         #     #[derive(Clone, PartialEq)]
+//!         #     enum GPULockRank { Nothing => { DeviceTracker }, DeviceTracker => { BufferMapState }, BufferMapState => {}, }
 //!         # }
 //!         # struct Tracker;
 //!         # struct BufferMapState;
@@ -94,8 +112,8 @@
 //!
 //!         # ordered_mutex::define_rank! {
 //!         #     static GPU_RANK;
-//!         #     #[derive(Clone, PartialOrd, PartialEq)]
-//!         #     enum GPULockRank { Nothing, DeviceTracker, BufferMapState, }
+//!         #     #[derive(Clone, PartialEq)]
+//!         #     enum GPULockRank { Nothing => { DeviceTracker }, DeviceTracker => { BufferMapState }, BufferMapState => {}, }
 //!         # }
 //!         # use ordered_mutex::Mutex;
 //!         # struct Tracker;
@@ -103,27 +121,66 @@
 //!         # struct Device { tracker: Mutex<Tracker, GPULockRank>, }
 //!         # struct Buffer { map_state: Mutex<BufferMapState, GPULockRank>, }
 //!         let device = Device {
-//!             tracker: Mutex::new(Tracker, GPULockRank::DeviceTracker),
+//!             tracker: Mutex::new(Tracker, GPULockRank::DeviceTracker, "tracker"),
 //!             // ...
 //!         };
 //!
 //!         let buffer = Buffer {
-//!             map_state: Mutex::new(BufferMapState, GPULockRank::BufferMapState),
+//!             map_state: Mutex::new(BufferMapState, GPULockRank::BufferMapState, "map_state"),
 //!             // ...
 //!         };
 //!
 //! 4)  Acquire and release locks as usual. If any thread ever tries to
-//!     acquire a lower-ranked lock while holding a higher-ranked
-//!     lock, the lock operation will panic.
-//!
-//! # Parking lot
-//!
-//! At the moment, this crate simply wraps the [`parking_lot`] crate's
-//! locks, but there's nothing about this instrumentation that is
-//! specific to `parking_lot`. In the future, this crate should
-//! provide generic types that can wrap any lock that provides the
-//! necessary interfaces. And it should support both `parking_lot` and
-//! the Rust standard library's locks out of the box.
+//!     acquire a lock whose rank isn't a follower of the youngest
+//!     rank it already holds, the lock operation will panic, naming
+//!     the offending lock (by the label you gave [`Mutex::new`]) along
+//!     with every currently held lock that doesn't list it as a
+//!     follower. Set `RUST_BACKTRACE` to have the panic include a
+//!     backtrace of the acquisition that triggered it.
+//!
+//! # Choice of lock implementation
+//!
+//! The rank-checking instrumentation above isn't specific to any one
+//! lock implementation, so [`Mutex`] and [`RwLock`] take a third,
+//! defaulted type parameter naming the backend they wrap: something
+//! implementing [`MutexBackend`] or [`RwLockBackend`] respectively.
+//! [`StdBackend`] and [`StdRwLockBackend`], the defaults, wrap the
+//! standard library's poisoning locks. Building with the
+//! `parking_lot` feature also makes [`ParkingLotBackend`] and
+//! [`ParkingLotRwLockBackend`] available, which wrap [`parking_lot`]'s
+//! locks; since those never poison, `Mutex::lock` and friends return
+//! the guard directly instead of a [`std::sync::LockResult`] when
+//! you choose them. Which shape you get is visible in the return
+//! type, via the backend's [`Poison`] association, rather than hidden
+//! behind a `Result` that can never actually be an `Err`.
+//!
+//! # Strict release order
+//!
+//! By default, lock guards may be dropped in any order: the ranking
+//! only cares about the order locks are *acquired* in, since that's
+//! what determines whether a deadlock can form. Building with the
+//! `strict_drop_order` feature additionally requires each thread to
+//! release its locks in the reverse of the order it acquired them, and
+//! panics if a guard is dropped while a more-recently-acquired one is
+//! still held. This catches cases where guards escape their natural
+//! nesting (for example, by being stored in a struct) when that's not
+//! what you intended.
+//!
+//! # Discovering a ranking by observation
+//!
+//! Working out the right ranking by hand can be hard, especially
+//! across callbacks and deep call stacks. Building with the
+//! `observe_locks` feature turns off ranking enforcement entirely:
+//! instead, every lock acquired while another is already held gets
+//! recorded as an `(outer, inner)` pair, tagged with the label you
+//! pass to [`Mutex::new`], and streamed to a file under the directory
+//! named by the `ORDERED_MUTEX_OBSERVE_DIR` environment variable (one
+//! file per thread, flushed as each pair is written, so that partial
+//! runs still yield data). The
+//! `ordered_mutex_analyze` binary merges those files, checks whether
+//! the resulting graph is acyclic, and prints a suggested
+//! `define_rank!` ranking (or, if your program's locking really is
+//! cyclic, the chain of acquisitions that proves it).
 //!
 //! # Why not atomics?
 //!
@@ -193,46 +250,268 @@
 //! `"adt_const_params"` feature would relax this restriction, but it
 //! doesn't seem to be a priority.
 
-use std::cell::RefCell;
+#[cfg(not(feature = "observe_locks"))]
+use std::cell::Cell;
+use std::cell::{RefCell, UnsafeCell};
 
+mod backend;
 mod rank_set;
+mod reentrant;
+
+#[cfg(feature = "observe_locks")]
+mod observe;
 
-use rank_set::RankSet;
+#[cfg(feature = "observe_locks")]
+pub mod analyze;
 
-pub trait Rank: PartialOrd + Into<u32> + Clone + Sized + 'static {
+pub use backend::{MutexBackend, NoPoison, Poison, RwLockBackend, StdBackend, StdPoison, StdRwLockBackend};
+#[cfg(feature = "parking_lot")]
+pub use backend::{ParkingLotBackend, ParkingLotRwLockBackend};
+pub use rank_set::RankSet;
+use reentrant::RawReentrant;
+
+pub trait Rank: Into<u32> + Clone + Sized + 'static {
     const CURRENT_RANK: &'static std::thread::LocalKey<ThreadState<Self>>;
+
+    /// Every rank this type defines. `define_rank!` builds this from
+    /// the variants listed in the `enum`, in declaration order. Used
+    /// only to check that the graph of `followers` edges is acyclic.
+    const ALL: &'static [Self];
+
+    /// The ranks that may be acquired next, if `self` is the youngest
+    /// rank the current thread holds.
+    fn followers(&self) -> RankSet<Self>;
+}
+
+#[cfg(not(feature = "observe_locks"))]
+pub struct ThreadState<R> {
+    // Each held rank's caller-supplied label (purely so an out-of-order
+    // panic can name the locks involved) and the identity of the lock
+    // instance it came from (so a lock can recognize re-acquiring
+    // itself, as opposed to some other lock of the same rank).
+    held: RefCell<Vec<(R, String, usize)>>,
+    acyclic_checked: Cell<bool>,
+}
+
+#[cfg(not(feature = "observe_locks"))]
+impl<R: Rank> ThreadState<R> {
+    pub const fn new() -> Self {
+        ThreadState {
+            held: RefCell::new(Vec::new()),
+            acyclic_checked: Cell::new(false),
+        }
+    }
+}
+
+#[cfg(not(feature = "observe_locks"))]
+impl<R: Rank> Default for ThreadState<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(feature = "observe_locks"))]
+impl<R: Rank> ThreadState<R> {
+    /// Check `rank` in. `identity` distinguishes this particular lock
+    /// instance from any other of the same rank; if `reentrant_ok` is
+    /// set and the youngest held lock has the same identity, the
+    /// acquisition is allowed even though a rank isn't its own
+    /// follower, since it's the same lock being re-acquired rather
+    /// than a second lock of the same rank.
+    fn lock(rank: R, label: String, identity: usize, reentrant_ok: bool) -> SavedState<R> {
+        R::CURRENT_RANK.with(|state| {
+            if !state.acyclic_checked.get() {
+                assert_acyclic::<R>();
+                state.acyclic_checked.set(true);
+            }
+
+            let held = state.held.borrow();
+            let allowed = match held.last() {
+                Some((youngest, _, youngest_identity)) => {
+                    (reentrant_ok && *youngest_identity == identity) || youngest.followers().contains(rank.clone())
+                }
+                None => true,
+            };
+            if !allowed {
+                let message = out_of_order_message(&rank, &label, held.as_slice());
+                drop(held);
+                panic!("{message}");
+            }
+            drop(held);
+
+            state.held.borrow_mut().push((rank.clone(), label, identity));
+        });
+        SavedState { rank }
+    }
+
+    fn unlock(rank: R) {
+        R::CURRENT_RANK.with(|state| {
+            let mut held = state.held.borrow_mut();
+            // By default, guards can be dropped in any order, so
+            // search for the matching rank rather than assuming it's
+            // on top of the stack. With `strict_drop_order` enabled,
+            // only dropping the top of the stack is allowed.
+            if let Some(pos) = held.iter().rposition(|(held_rank, _, _)| rank_bit(held_rank) == rank_bit(&rank)) {
+                #[cfg(feature = "strict_drop_order")]
+                if pos != held.len() - 1 {
+                    let (_, label, _) = &held[pos];
+                    panic!(
+                        "ordered_mutex: lock \"{label}\" (rank {}) was dropped out of order; \
+                         strict_drop_order requires locks to be released in the reverse of \
+                         the order they were acquired",
+                        rank_bit(&rank),
+                    );
+                }
+                held.remove(pos);
+            }
+        });
+    }
+}
+
+/// Build the panic message for an out-of-order acquisition: names the
+/// rank and label of the lock being acquired, and every currently held
+/// lock whose rank doesn't list it as a follower (the locks actually
+/// responsible for the violation), followed by the full held stack for
+/// context. Appends a backtrace when `RUST_BACKTRACE` asks for one.
+#[cfg(not(feature = "observe_locks"))]
+fn out_of_order_message<R: Rank>(rank: &R, label: &str, held: &[(R, String, usize)]) -> String {
+    let blockers: Vec<String> = held
+        .iter()
+        .filter(|(held_rank, _, _)| !held_rank.followers().contains(rank.clone()))
+        .map(|(held_rank, held_label, _)| format!("\"{held_label}\" (rank {})", rank_bit(held_rank)))
+        .collect();
+
+    let stack: Vec<String> = held
+        .iter()
+        .map(|(held_rank, held_label, _)| format!("\"{held_label}\" (rank {})", rank_bit(held_rank)))
+        .collect();
+
+    let mut message = format!(
+        "ordered_mutex: attempted to acquire lock \"{label}\" (rank {}) out of order: \
+         it is not listed as a follower of: {}\n  currently held locks (outermost first): {}",
+        rank_bit(rank),
+        blockers.join(", "),
+        stack.join(", "),
+    );
+
+    let backtrace = std::backtrace::Backtrace::capture();
+    if backtrace.status() == std::backtrace::BacktraceStatus::Captured {
+        message.push_str(&format!("\n{backtrace}"));
+    }
+
+    message
 }
 
+// In observation mode, nothing is enforced: we just record every
+// `(outer, inner)` nesting pair we see, so `analyze` can propose a
+// ranking afterwards. The held stack carries each rank's caller-supplied
+// label along with it, since that's what makes the recorded pairs
+// readable.
+#[cfg(feature = "observe_locks")]
 pub struct ThreadState<R> {
-    current_rank: RefCell<RankSet<R>>,
+    held: RefCell<Vec<(R, String)>>,
 }
 
+#[cfg(feature = "observe_locks")]
 impl<R: Rank> ThreadState<R> {
     pub const fn new() -> Self {
         ThreadState {
-            current_rank: RefCell::new(RankSet::new()),
+            held: RefCell::new(Vec::new()),
         }
     }
 }
 
+#[cfg(feature = "observe_locks")]
+impl<R: Rank> Default for ThreadState<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "observe_locks")]
 impl<R: Rank> ThreadState<R> {
-    fn lock(rank: R) -> SavedState<R> {
+    // `identity`/`reentrant_ok` only matter to the enforcement path;
+    // observation mode never checks whether an acquisition is allowed.
+    fn lock(rank: R, label: String, _identity: usize, _reentrant_ok: bool) -> SavedState<R> {
         R::CURRENT_RANK.with(|state| {
-            assert!(
-                !state.current_rank.borrow_mut().insert(rank.clone()),
-                "Attempted to acquire lock out of order"
-            );
+            if let Some((outer_rank, outer_label)) = state.held.borrow().last() {
+                crate::observe::record_nesting(outer_label, rank_bit(outer_rank), &label, rank_bit(&rank));
+            }
+            state.held.borrow_mut().push((rank.clone(), label));
         });
         SavedState { rank }
     }
 
     fn unlock(rank: R) {
         R::CURRENT_RANK.with(|state| {
-            state.current_rank.borrow_mut().remove(rank);
+            let mut held = state.held.borrow_mut();
+            if let Some(pos) = held.iter().rposition(|(held_rank, _)| rank_bit(held_rank) == rank_bit(&rank)) {
+                held.remove(pos);
+            }
         });
     }
 }
 
+/// Return the bit position `rank` occupies in a [`RankSet`].
+fn rank_bit<R: Into<u32> + Clone>(rank: &R) -> u32 {
+    rank.clone().into()
+}
+
+/// Walk `R`'s follower graph and panic if it contains a cycle. A
+/// cyclic ranking can't prevent deadlocks, since a thread could hold
+/// a lock, follow the cycle back around, and try to acquire a lock
+/// that (transitively) depends on the one it started with. A
+/// self-loop (a rank listing itself as its own follower) is a cycle
+/// too: the rank check that enforces this graph only looks at rank,
+/// not lock identity, so a self-loop would let a thread acquire any
+/// second, unrelated lock of that rank, not just re-acquire the one
+/// it already holds. Re-acquiring the same lock is handled instead by
+/// [`RwLock::read`](crate::RwLock::read) and
+/// [`ReentrantMutex`](crate::ReentrantMutex), which check lock
+/// identity rather than rank.
+#[cfg(not(feature = "observe_locks"))]
+fn assert_acyclic<R: Rank>() {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Mark {
+        Unvisited,
+        InProgress,
+        Done,
+    }
+
+    fn visit<R: Rank>(i: usize, all: &[R], marks: &mut [Mark], path: &mut Vec<usize>) {
+        match marks[i] {
+            Mark::Done => return,
+            Mark::InProgress => {
+                let start = path.iter().position(|&j| j == i).expect("in-progress rank must be on the path");
+                let cycle: Vec<u32> = path[start..].iter().map(|&j| rank_bit(&all[j])).collect();
+                panic!(
+                    "ordered_mutex: rank graph is not acyclic; ranks {:?} form a cycle of followers",
+                    cycle
+                );
+            }
+            Mark::Unvisited => {}
+        }
+
+        marks[i] = Mark::InProgress;
+        path.push(i);
+        let followers = all[i].followers();
+        for (j, other) in all.iter().enumerate() {
+            if followers.contains(other.clone()) {
+                visit(j, all, marks, path);
+            }
+        }
+        path.pop();
+        marks[i] = Mark::Done;
+    }
+
+    let all = R::ALL;
+    let mut marks = vec![Mark::Unvisited; all.len()];
+    for i in 0..all.len() {
+        let mut path = Vec::new();
+        visit(i, all, &mut marks, &mut path);
+    }
+}
+
 struct SavedState<R: Rank> {
     rank: R,
 }
@@ -243,39 +522,133 @@ impl<R: Rank> Drop for SavedState<R> {
     }
 }
 
-pub struct Mutex<T, R: Rank> {
-    inner: std::sync::Mutex<T>,
+/// Check in `rank` with the current thread's [`ThreadState`] and
+/// return the [`SavedState`] that checks it back out on drop. Shared
+/// by `Mutex::lock` and `RwLock`'s `read`/`write`, since the rank
+/// check doesn't care which kind of lock is being acquired.
+///
+/// `identity` identifies the specific lock instance being acquired, so
+/// that `reentrant_ok` callers (currently just `RwLock::read`) can
+/// permit re-acquiring the very same lock without requiring its rank
+/// to list itself as a follower — which would also wrongly permit
+/// acquiring a second, unrelated lock of that rank.
+fn acquire<R: Rank>(rank: &R, label: &str, identity: usize, reentrant_ok: bool) -> SavedState<R> {
+    ThreadState::lock(rank.clone(), label.to_string(), identity, reentrant_ok)
+}
+
+/// A value that identifies `value`'s address, stable for as long as
+/// `value` doesn't move — used to tell "the same lock, acquired again"
+/// apart from "a different lock of the same rank".
+fn identity<T>(value: &T) -> usize {
+    value as *const T as usize
+}
+
+pub struct Mutex<T, R: Rank, B: MutexBackend<T> = StdBackend<T>> {
+    inner: B,
     rank: R,
+    label: String,
+    _value: std::marker::PhantomData<T>,
 }
 
-pub struct MutexGuard<'a, T: 'a, R: Rank> {
-    inner: std::sync::MutexGuard<'a, T>,
+pub struct MutexGuard<'a, T: 'a, R: Rank, B: MutexBackend<T> + 'a = StdBackend<T>> {
+    inner: B::Guard<'a>,
 
     #[allow(dead_code)] // held for its `Drop`
     saved_state: SavedState<R>,
 }
 
-impl<T, R: Rank> Mutex<T, R> {
-    pub fn new(value: T, rank: R) -> Self {
+impl<T, R: Rank, B: MutexBackend<T>> Mutex<T, R, B> {
+    pub fn new(value: T, rank: R, label: impl Into<String>) -> Self {
         Mutex {
-            inner: std::sync::Mutex::new(value),
+            inner: B::new(value),
             rank,
+            label: label.into(),
+            _value: std::marker::PhantomData,
         }
     }
 
-    pub fn lock(&self) -> std::sync::LockResult<MutexGuard<T, R>> {
-        let saved_state = ThreadState::lock(self.rank.clone());
-        match self.inner.lock() {
-            Ok(inner) => Ok(MutexGuard { inner, saved_state }),
-            Err(inner_poison_error) => Err(std::sync::PoisonError::new(MutexGuard {
-                inner: inner_poison_error.into_inner(),
-                saved_state,
-            })),
+    pub fn lock(&self) -> <B::Poison as Poison>::Wrapped<'_, MutexGuard<'_, T, R, B>> {
+        let saved_state = acquire(&self.rank, &self.label, identity(self), false);
+        let (inner, poisoned) = self.inner.raw_lock();
+        B::Poison::wrap(MutexGuard { inner, saved_state }, poisoned)
+    }
+}
+
+impl<'a, T, R: Rank, B: MutexBackend<T> + 'a> std::ops::Deref for MutexGuard<'a, T, R, B> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.inner.deref()
+    }
+}
+
+impl<'a, T, R: Rank, B: MutexBackend<T> + 'a> std::ops::DerefMut for MutexGuard<'a, T, R, B> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.inner.deref_mut()
+    }
+}
+
+pub struct RwLock<T, R: Rank, B: RwLockBackend<T> = StdRwLockBackend<T>> {
+    inner: B,
+    rank: R,
+    label: String,
+    _value: std::marker::PhantomData<T>,
+}
+
+pub struct RwLockReadGuard<'a, T: 'a, R: Rank, B: RwLockBackend<T> + 'a = StdRwLockBackend<T>> {
+    inner: B::ReadGuard<'a>,
+
+    #[allow(dead_code)] // held for its `Drop`
+    saved_state: SavedState<R>,
+}
+
+pub struct RwLockWriteGuard<'a, T: 'a, R: Rank, B: RwLockBackend<T> + 'a = StdRwLockBackend<T>> {
+    inner: B::WriteGuard<'a>,
+
+    #[allow(dead_code)] // held for its `Drop`
+    saved_state: SavedState<R>,
+}
+
+impl<T, R: Rank, B: RwLockBackend<T>> RwLock<T, R, B> {
+    pub fn new(value: T, rank: R, label: impl Into<String>) -> Self {
+        RwLock {
+            inner: B::new(value),
+            rank,
+            label: label.into(),
+            _value: std::marker::PhantomData,
         }
     }
+
+    /// Acquire this lock for reading. Like [`Mutex::lock`], this
+    /// checks the rank against the current thread's held ranks before
+    /// blocking. Re-acquiring a read lock that's already held by this
+    /// thread is permitted as long as that lock is still the youngest
+    /// one the thread holds, since it's recognized by lock
+    /// identity rather than by rank — a rank can never list itself as
+    /// a follower, so this is the only way to acquire two locks of the
+    /// same rank on one thread.
+    pub fn read(&self) -> <B::Poison as Poison>::Wrapped<'_, RwLockReadGuard<'_, T, R, B>> {
+        let saved_state = acquire(&self.rank, &self.label, identity(self), true);
+        let (inner, poisoned) = self.inner.raw_read();
+        B::Poison::wrap(RwLockReadGuard { inner, saved_state }, poisoned)
+    }
+
+    pub fn write(&self) -> <B::Poison as Poison>::Wrapped<'_, RwLockWriteGuard<'_, T, R, B>> {
+        let saved_state = acquire(&self.rank, &self.label, identity(self), false);
+        let (inner, poisoned) = self.inner.raw_write();
+        B::Poison::wrap(RwLockWriteGuard { inner, saved_state }, poisoned)
+    }
+}
+
+impl<'a, T, R: Rank, B: RwLockBackend<T> + 'a> std::ops::Deref for RwLockReadGuard<'a, T, R, B> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.inner.deref()
+    }
 }
 
-impl<'a, T, R: Rank> std::ops::Deref for MutexGuard<'a, T, R> {
+impl<'a, T, R: Rank, B: RwLockBackend<T> + 'a> std::ops::Deref for RwLockWriteGuard<'a, T, R, B> {
     type Target = T;
 
     fn deref(&self) -> &Self::Target {
@@ -283,12 +656,124 @@ impl<'a, T, R: Rank> std::ops::Deref for MutexGuard<'a, T, R> {
     }
 }
 
-impl<'a, T, R: Rank> std::ops::DerefMut for MutexGuard<'a, T, R> {
+impl<'a, T, R: Rank, B: RwLockBackend<T> + 'a> std::ops::DerefMut for RwLockWriteGuard<'a, T, R, B> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         self.inner.deref_mut()
     }
 }
 
+/// A mutex that the thread already holding it can lock again without
+/// blocking or tripping the rank check, for recursive data structures
+/// and callback-style code that naturally re-enters its own lock.
+///
+/// Because a nested acquisition might alias a reference handed to an
+/// outer one, [`ReentrantMutexGuard`] only derefs to `&T`, not `&mut
+/// T` — the same restriction the standard library's own reentrant
+/// lock places on its guard.
+pub struct ReentrantMutex<T, R: Rank> {
+    raw: RawReentrant,
+    value: UnsafeCell<T>,
+
+    // The `SavedState` that keeps this thread's held-rank stack in
+    // sync with the raw lock, created on the outermost `lock()` and
+    // dropped on whichever guard's `Drop` observes `raw`'s recursion
+    // count fall back to zero — not necessarily the first guard
+    // acquired, since guards may be dropped in any order. Only ever
+    // touched by the thread that currently holds `raw`, so it's safe
+    // to access through the `UnsafeCell` the same way `value` is.
+    saved_state: UnsafeCell<Option<SavedState<R>>>,
+
+    rank: R,
+    label: String,
+}
+
+// Safety: `raw` ensures only one thread is ever inside the critical
+// section (recursively, on the same thread), so shared access to
+// `value` and `saved_state` across threads is as safe as it is for
+// `std::sync::Mutex`.
+unsafe impl<T: Send, R: Rank + Sync> Sync for ReentrantMutex<T, R> {}
+
+/// The guard returned by [`ReentrantMutex::lock`]. Can't be sent to
+/// another thread: only the thread that acquired it is allowed to
+/// release it, since that's the thread `RawReentrant` considers the
+/// owner.
+///
+/// ```compile_fail
+/// # ordered_mutex::define_rank! {
+/// #     static GUARD_SEND_RANK;
+/// #     #[derive(Clone)]
+/// #     enum GuardSendRank { Data => {} }
+/// # }
+/// # use ordered_mutex::ReentrantMutex;
+/// let lock: ReentrantMutex<i32, GuardSendRank> = ReentrantMutex::new(0, GuardSendRank::Data, "data");
+/// let guard = lock.lock();
+/// std::thread::spawn(move || {
+///     let _guard = guard;
+/// });
+/// ```
+pub struct ReentrantMutexGuard<'a, T: 'a, R: Rank> {
+    lock: &'a ReentrantMutex<T, R>,
+
+    // A guard must only ever be released by the thread that acquired
+    // it, since that's what `RawReentrant` uses to track which thread
+    // currently owns the lock -- handing one to another thread would
+    // let two threads read `T` concurrently despite no `Sync` bound on
+    // `T`. Negative trait impls aren't stable, so this `*const ()`
+    // marker (neither `Send` nor `Sync`) opts the guard out of both
+    // the same way the standard library's own (nightly)
+    // `ReentrantLockGuard` opts out of `Send` directly.
+    _not_send: std::marker::PhantomData<*const ()>,
+}
+
+impl<T, R: Rank> ReentrantMutex<T, R> {
+    pub fn new(value: T, rank: R, label: impl Into<String>) -> Self {
+        ReentrantMutex {
+            raw: RawReentrant::new(),
+            value: UnsafeCell::new(value),
+            saved_state: UnsafeCell::new(None),
+            rank,
+            label: label.into(),
+        }
+    }
+
+    pub fn lock(&self) -> ReentrantMutexGuard<'_, T, R> {
+        let outermost = self.raw.lock();
+        if outermost {
+            // Safety: `raw.lock()` just returned `true`, so this
+            // thread is the sole holder and no other thread can be
+            // touching `saved_state`.
+            unsafe { *self.saved_state.get() = Some(acquire(&self.rank, &self.label, identity(self), false)) };
+        }
+        ReentrantMutexGuard {
+            lock: self,
+            _not_send: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<'a, T, R: Rank> std::ops::Deref for ReentrantMutexGuard<'a, T, R> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: `RawReentrant::lock` guarantees this thread holds
+        // the lock (possibly recursively) for as long as this guard
+        // exists, and every guard only ever reads `T` through it.
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<'a, T, R: Rank> Drop for ReentrantMutexGuard<'a, T, R> {
+    fn drop(&mut self) {
+        if self.lock.raw.unlock() {
+            // Safety: `raw.unlock()` just returned `true`, meaning
+            // this was the last recursive hold released — this
+            // thread no longer holds the lock, and no other thread
+            // could have touched `saved_state` while it did.
+            unsafe { *self.lock.saved_state.get() = None };
+        }
+    }
+}
+
 #[macro_export]
 macro_rules! define_rank {
     {
@@ -297,7 +782,7 @@ macro_rules! define_rank {
 
         $( #[ $( $type_attr:meta ),* ] )*
         enum $rank_type:ident {
-            $( $variant:ident, )*
+            $( $variant:ident => { $( $follower:ident ),* $(,)? } ),* $(,)?
         }
     } => {
         $( #[ $( $type_attr ),* ] )*
@@ -312,6 +797,19 @@ macro_rules! define_rank {
 
         impl $crate::Rank for $rank_type {
             const CURRENT_RANK: &'static std::thread::LocalKey<$crate::ThreadState<Self>> = &$current_rank;
+
+            const ALL: &'static [Self] = &[ $( $rank_type::$variant ),* ];
+
+            fn followers(&self) -> $crate::RankSet<Self> {
+                match self {
+                    $(
+                        $rank_type::$variant => {
+                            $crate::RankSet::new()
+                                $( .with($rank_type::$follower) )*
+                        }
+                    )*
+                }
+            }
         }
 
         impl From<$rank_type> for u32 {
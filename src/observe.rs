@@ -0,0 +1,47 @@
+//! Per-thread logging for the `observe_locks` feature.
+//!
+//! Each thread that acquires a lock while already holding another,
+//! with `observe_locks` enabled, gets its own log file (created on its
+//! first such acquisition) in the directory named by the
+//! `ORDERED_MUTEX_OBSERVE_DIR` environment variable, so that observing
+//! a multithreaded program doesn't require any cross-thread
+//! synchronization. Events are flushed as they're written, so a
+//! program that's killed partway through an observed run still leaves
+//! behind usable data.
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_THREAD_FILE: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static LOG: RefCell<Option<File>> = const { RefCell::new(None) };
+}
+
+/// Record that a lock labelled `inner_label`, at rank `inner_rank`,
+/// was acquired while the current thread already held a lock labelled
+/// `outer_label`, at rank `outer_rank`.
+pub(crate) fn record_nesting(outer_label: &str, outer_rank: u32, inner_label: &str, inner_rank: u32) {
+    LOG.with(|log| {
+        let mut log = log.borrow_mut();
+        if log.is_none() {
+            *log = open_log();
+        }
+        let Some(file) = log.as_mut() else { return };
+
+        // Observation is best-effort: a write failure shouldn't take
+        // down the program being studied.
+        let _ = writeln!(file, "{outer_label}\t{outer_rank}\t{inner_label}\t{inner_rank}");
+        let _ = file.flush();
+    });
+}
+
+fn open_log() -> Option<File> {
+    let dir = std::env::var_os("ORDERED_MUTEX_OBSERVE_DIR")?;
+    let dir = std::path::PathBuf::from(dir);
+    std::fs::create_dir_all(&dir).ok()?;
+    let index = NEXT_THREAD_FILE.fetch_add(1, Ordering::Relaxed);
+    File::create(dir.join(format!("thread-{index}.log"))).ok()
+}
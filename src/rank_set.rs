@@ -3,9 +3,18 @@ pub struct RankSet<R> {
     _rank: std::marker::PhantomData<R>,
 }
 
+impl<R> Default for RankSet<R>
+where
+    R: Into<u32>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<R> RankSet<R>
 where
-    R: Into<u32> + PartialOrd,
+    R: Into<u32>,
 {
     pub const fn new() -> Self {
         RankSet {
@@ -14,22 +23,31 @@ where
         }
     }
 
-    /// Insert `elt` into this set, and return `true` if the set
-    /// previously contained anything greater than or equal to `elt`.
+    /// Add `elt` to this set.
     #[inline]
-    pub fn insert(&mut self, elt: R) -> bool {
-        let bit = 1_u64 << elt.into();
-
-        // Create a bitmask that includes `bit` and all bits of higher value.
-        let greater_than_or_equal = !(bit - 1);
-        let result = self.bitset & greater_than_or_equal != 0;
-        self.bitset |= bit;
-        result
+    pub fn insert(&mut self, elt: R) {
+        self.bitset |= 1_u64 << elt.into();
     }
 
+    /// Remove `elt` from this set.
     #[inline]
     pub fn remove(&mut self, elt: R) {
-        let bit = 1 << elt.into();
-        self.bitset &= !bit;
+        self.bitset &= !(1_u64 << elt.into());
+    }
+
+    /// Return `true` if this set contains `elt`.
+    #[inline]
+    pub fn contains(&self, elt: R) -> bool {
+        self.bitset & (1_u64 << elt.into()) != 0
+    }
+
+    /// Add `elt` to this set and return `self`, for building up a
+    /// [`RankSet`] in a single expression. This is what
+    /// `define_rank!`-generated `followers` implementations use to
+    /// construct the set of successor ranks for each variant.
+    #[inline]
+    pub fn with(mut self, elt: R) -> Self {
+        self.insert(elt);
+        self
     }
 }
@@ -0,0 +1,69 @@
+//! The raw mutual-exclusion primitive behind [`ReentrantMutex`](crate::ReentrantMutex).
+//!
+//! `std::sync::Mutex` isn't reentrant: a thread that calls `lock()`
+//! while it already holds the lock blocks forever. So `ReentrantMutex`
+//! can't just wrap one; instead, this tracks the owning thread and a
+//! recursion count behind an ordinary mutex and condvar, following the
+//! same owner-and-count approach as the standard library's own
+//! (nightly) `ReentrantLock`.
+
+use std::sync::{Condvar, Mutex};
+use std::thread::ThreadId;
+
+struct State {
+    owner: Option<ThreadId>,
+    count: usize,
+}
+
+pub(crate) struct RawReentrant {
+    state: Mutex<State>,
+    unlocked: Condvar,
+}
+
+impl RawReentrant {
+    pub(crate) fn new() -> Self {
+        RawReentrant {
+            state: Mutex::new(State { owner: None, count: 0 }),
+            unlocked: Condvar::new(),
+        }
+    }
+
+    /// Block until this thread holds the lock, then return `true` if
+    /// this acquisition is the outermost one (the thread didn't
+    /// already hold it), or `false` if it's a recursive re-entry.
+    pub(crate) fn lock(&self) -> bool {
+        let me = std::thread::current().id();
+        let mut state = self.state.lock().unwrap();
+        loop {
+            match state.owner {
+                Some(owner) if owner == me => {
+                    state.count += 1;
+                    return false;
+                }
+                None => {
+                    state.owner = Some(me);
+                    state.count = 1;
+                    return true;
+                }
+                Some(_) => {
+                    state = self.unlocked.wait(state).unwrap();
+                }
+            }
+        }
+    }
+
+    /// Release one level of recursion, waking a waiting thread once
+    /// the last one has been released. Returns `true` if this was that
+    /// last level — the thread no longer holds the lock at all.
+    pub(crate) fn unlock(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.count -= 1;
+        if state.count == 0 {
+            state.owner = None;
+            self.unlocked.notify_one();
+            true
+        } else {
+            false
+        }
+    }
+}
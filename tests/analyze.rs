@@ -0,0 +1,44 @@
+#![cfg(feature = "observe_locks")]
+
+use ordered_mutex::analyze::ObservedGraph;
+use std::io::Write;
+
+/// Write `lines` (already tab-separated, one nesting pair per line) to
+/// a fresh `*.log` file in a scratch directory, and return that
+/// directory for `ObservedGraph::read_dir`.
+fn log_dir(name: &str, lines: &[&str]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("ordered_mutex_analyze_test-{name}-{:?}", std::thread::current().id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut file = std::fs::File::create(dir.join("thread-0.log")).unwrap();
+    for line in lines {
+        writeln!(file, "{line}").unwrap();
+    }
+    dir
+}
+
+#[test]
+fn acyclic_graph_has_no_cycle() {
+    let dir = log_dir("acyclic", &["tracker\t0\tmap_state\t1"]);
+    let graph = ObservedGraph::read_dir(&dir).unwrap();
+    assert!(graph.find_cycle().is_none());
+}
+
+#[test]
+fn cyclic_graph_reports_the_cycle() {
+    let dir = log_dir("cyclic", &["tracker\t0\tmap_state\t1", "map_state\t1\ttracker\t0"]);
+    let graph = ObservedGraph::read_dir(&dir).unwrap();
+    let cycle = graph.find_cycle().expect("graph has a tracker <-> map_state cycle");
+    let chain: Vec<&str> = cycle.iter().map(|&rank| graph.label(rank)).collect();
+    assert_eq!(chain.first(), chain.last());
+    assert!(chain.contains(&"tracker"));
+    assert!(chain.contains(&"map_state"));
+}
+
+#[test]
+fn suggest_ranking_lists_each_ranks_followers() {
+    let dir = log_dir("suggest", &["tracker\t0\tmap_state\t1"]);
+    let graph = ObservedGraph::read_dir(&dir).unwrap();
+    let suggestion = graph.suggest_ranking();
+    assert!(suggestion.contains("tracker => { map_state }"));
+    assert!(suggestion.contains("map_state => {  }"));
+}
@@ -4,10 +4,10 @@ ordered_mutex::define_rank! {
 
     /// Order in which GPU locks must be acquired.
     #[repr(u32)]
-    #[derive(Clone, PartialOrd, PartialEq)]
+    #[derive(Clone, PartialEq)]
     enum GPULockRank {
-        DeviceTracker,
-        BufferMapState,
+        DeviceTracker => { BufferMapState },
+        BufferMapState => {},
     }
 }
 
@@ -29,10 +29,10 @@ struct Buffer {
 #[test]
 fn in_order() {
     let device = Device {
-        tracker: Mutex::new(Tracker, GPULockRank::DeviceTracker),
+        tracker: Mutex::new(Tracker, GPULockRank::DeviceTracker, "tracker"),
     };
     let buffer = Buffer {
-        map_state: Mutex::new(BufferMapState, GPULockRank::BufferMapState),
+        map_state: Mutex::new(BufferMapState, GPULockRank::BufferMapState, "map_state"),
     };
 
     {
@@ -54,14 +54,37 @@ fn in_order() {
     }
 }
 
+// The panic message should name both the lock being acquired and the
+// lock blocking it, so whoever hits this in the wild knows which two
+// locks to look at.
+#[test]
+fn out_of_order_message_names_both_locks() {
+    let device = Device {
+        tracker: Mutex::new(Tracker, GPULockRank::DeviceTracker, "tracker"),
+    };
+    let buffer = Buffer {
+        map_state: Mutex::new(BufferMapState, GPULockRank::BufferMapState, "map_state"),
+    };
+
+    let _map_state_guard = buffer.map_state.lock().unwrap();
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        device.tracker.lock().unwrap();
+    }));
+    let panic_payload = result.unwrap_err();
+    let message = panic_payload.downcast_ref::<String>().expect("panic payload should be a String");
+
+    assert!(message.contains("\"tracker\""));
+    assert!(message.contains("\"map_state\""));
+}
+
 #[test]
 #[should_panic]
 fn out_of_order() {
     let device = Device {
-        tracker: Mutex::new(Tracker, GPULockRank::DeviceTracker),
+        tracker: Mutex::new(Tracker, GPULockRank::DeviceTracker, "tracker"),
     };
     let buffer = Buffer {
-        map_state: Mutex::new(BufferMapState, GPULockRank::BufferMapState),
+        map_state: Mutex::new(BufferMapState, GPULockRank::BufferMapState, "map_state"),
     };
 
     let _map_state_guard = buffer.map_state.lock().unwrap();
@@ -69,10 +92,13 @@ fn out_of_order() {
 }
 
 // Dropping lock guards out of order should still clear the state.
+// This is exactly what `strict_drop_order` forbids, so it doesn't
+// apply when that feature is enabled.
+#[cfg(not(feature = "strict_drop_order"))]
 #[test]
 fn staggered_clear() {
-    let tracker: Mutex<(), GPULockRank> = Mutex::new((), GPULockRank::DeviceTracker);
-    let map_state: Mutex<(), GPULockRank> = Mutex::new((), GPULockRank::BufferMapState);
+    let tracker: Mutex<(), GPULockRank> = Mutex::new((), GPULockRank::DeviceTracker, "tracker");
+    let map_state: Mutex<(), GPULockRank> = Mutex::new((), GPULockRank::BufferMapState, "map_state");
 
     let tracker_guard = tracker.lock().unwrap();
     let map_state_guard = map_state.lock().unwrap();
@@ -85,12 +111,16 @@ fn staggered_clear() {
     let _second_tracker_guard = tracker.lock().unwrap();
 }
 
-// Dropping lock guards out of order should retain other guards.
+// Dropping lock guards out of order should retain other guards. Under
+// `strict_drop_order` this same drop still panics, but for the wrong
+// reason (the strict-order violation rather than the stale rank this
+// test means to prove), so skip it there too.
+#[cfg(not(feature = "strict_drop_order"))]
 #[test]
 #[should_panic]
 fn staggered_retain() {
-    let tracker: Mutex<(), GPULockRank> = Mutex::new((), GPULockRank::DeviceTracker);
-    let map_state: Mutex<(), GPULockRank> = Mutex::new((), GPULockRank::BufferMapState);
+    let tracker: Mutex<(), GPULockRank> = Mutex::new((), GPULockRank::DeviceTracker, "tracker");
+    let map_state: Mutex<(), GPULockRank> = Mutex::new((), GPULockRank::BufferMapState, "map_state");
 
     let tracker_guard = tracker.lock().unwrap();
     let _map_state_guard = map_state.lock().unwrap();
@@ -100,3 +130,195 @@ fn staggered_retain() {
     drop(tracker_guard);
     let _second_tracker_guard = tracker.lock().unwrap();
 }
+
+// With `strict_drop_order` enabled, dropping guards in the reverse of
+// the order they were acquired is still fine.
+#[cfg(feature = "strict_drop_order")]
+#[test]
+fn strict_drop_order_allows_reverse_order() {
+    let tracker: Mutex<(), GPULockRank> = Mutex::new((), GPULockRank::DeviceTracker, "tracker");
+    let map_state: Mutex<(), GPULockRank> = Mutex::new((), GPULockRank::BufferMapState, "map_state");
+
+    let tracker_guard = tracker.lock().unwrap();
+    let map_state_guard = map_state.lock().unwrap();
+
+    drop(map_state_guard);
+    drop(tracker_guard);
+}
+
+// With `strict_drop_order` enabled, dropping a guard while a
+// later-acquired one is still held must panic.
+#[cfg(feature = "strict_drop_order")]
+#[test]
+#[should_panic]
+fn strict_drop_order_rejects_out_of_order_drop() {
+    let tracker: Mutex<(), GPULockRank> = Mutex::new((), GPULockRank::DeviceTracker, "tracker");
+    let map_state: Mutex<(), GPULockRank> = Mutex::new((), GPULockRank::BufferMapState, "map_state");
+
+    let tracker_guard = tracker.lock().unwrap();
+    let _map_state_guard = map_state.lock().unwrap();
+
+    drop(tracker_guard);
+}
+
+use ordered_mutex::RwLock;
+
+#[test]
+fn rwlock_in_order() {
+    let tracker: RwLock<(), GPULockRank> = RwLock::new((), GPULockRank::DeviceTracker, "tracker");
+    let map_state: RwLock<(), GPULockRank> = RwLock::new((), GPULockRank::BufferMapState, "map_state");
+
+    let _tracker_guard = tracker.read().unwrap();
+    let _map_state_guard = map_state.write().unwrap();
+}
+
+#[test]
+#[should_panic]
+fn rwlock_out_of_order() {
+    let tracker: RwLock<(), GPULockRank> = RwLock::new((), GPULockRank::DeviceTracker, "tracker");
+    let map_state: RwLock<(), GPULockRank> = RwLock::new((), GPULockRank::BufferMapState, "map_state");
+
+    let _map_state_guard = map_state.read().unwrap();
+    let _tracker_guard = tracker.write().unwrap();
+}
+
+#[test]
+fn rwlock_read_is_reentrant() {
+    let map_state: RwLock<(), GPULockRank> = RwLock::new((), GPULockRank::BufferMapState, "map_state");
+
+    // Reading a lock already held for reading on this thread is
+    // allowed, even though `BufferMapState` lists no followers.
+    let _outer_guard = map_state.read().unwrap();
+    let _inner_guard = map_state.read().unwrap();
+}
+
+#[test]
+#[should_panic]
+fn rwlock_read_reentrancy_is_per_instance() {
+    let map_state: RwLock<(), GPULockRank> = RwLock::new((), GPULockRank::BufferMapState, "map_state");
+    let other_map_state: RwLock<(), GPULockRank> = RwLock::new((), GPULockRank::BufferMapState, "other_map_state");
+
+    // Permitting re-entry into the very same lock must not also permit
+    // acquiring a different lock that merely shares its rank.
+    let _outer_guard = map_state.read().unwrap();
+    let _other_guard = other_map_state.read().unwrap();
+}
+
+#[test]
+fn mutex_with_explicit_std_backend() {
+    use ordered_mutex::StdBackend;
+
+    let tracker: Mutex<Tracker, GPULockRank, StdBackend<Tracker>> =
+        Mutex::new(Tracker, GPULockRank::DeviceTracker, "tracker");
+    let _guard = tracker.lock().unwrap();
+}
+
+#[cfg(feature = "parking_lot")]
+#[test]
+fn mutex_with_parking_lot_backend() {
+    use ordered_mutex::ParkingLotBackend;
+
+    let tracker: Mutex<Tracker, GPULockRank, ParkingLotBackend<Tracker>> =
+        Mutex::new(Tracker, GPULockRank::DeviceTracker, "tracker");
+    // `parking_lot` never poisons, so the guard comes back unwrapped.
+    let _guard = tracker.lock();
+}
+
+use ordered_mutex::ReentrantMutex;
+
+#[test]
+fn reentrant_mutex_allows_recursion() {
+    let tracker: ReentrantMutex<Tracker, GPULockRank> = ReentrantMutex::new(Tracker, GPULockRank::DeviceTracker, "tracker");
+
+    let _outer_guard = tracker.lock();
+    // Locking the same `ReentrantMutex` again from the thread that
+    // already holds it must not panic or deadlock.
+    let _inner_guard = tracker.lock();
+}
+
+#[test]
+#[should_panic]
+fn reentrant_mutex_retains_rank_until_last_guard_drops() {
+    let tracker: ReentrantMutex<Tracker, GPULockRank> = ReentrantMutex::new(Tracker, GPULockRank::BufferMapState, "tracker");
+    let other: Mutex<BufferMapState, GPULockRank> = Mutex::new(BufferMapState, GPULockRank::BufferMapState, "other");
+
+    let outer_guard = tracker.lock();
+    let _inner_guard = tracker.lock();
+
+    // Guards may be dropped in any order. Dropping the outermost one
+    // here must not release this thread's hold on the lock's rank,
+    // since the thread still physically holds the `ReentrantMutex`
+    // through `_inner_guard`.
+    drop(outer_guard);
+    let _other_guard = other.lock().unwrap();
+}
+
+ordered_mutex::define_rank! {
+    static PIPELINE_RANK;
+
+    #[repr(u32)]
+    #[derive(Clone, PartialEq)]
+    enum PipelineRank {
+        Pipeline => { ShaderCache, TextureCache },
+        ShaderCache => {},
+        TextureCache => {},
+    }
+}
+
+#[test]
+fn unrelated_siblings_need_no_mutual_order() {
+    let pipeline: Mutex<(), PipelineRank> = Mutex::new((), PipelineRank::Pipeline, "pipeline");
+    let shader_cache: Mutex<(), PipelineRank> = Mutex::new((), PipelineRank::ShaderCache, "shader_cache");
+    let texture_cache: Mutex<(), PipelineRank> = Mutex::new((), PipelineRank::TextureCache, "texture_cache");
+
+    // ShaderCache and TextureCache don't list each other as a
+    // follower: they're unrelated subsystems that both nest under
+    // Pipeline, and neither needs to be ordered against the other.
+    {
+        let _pipeline_guard = pipeline.lock().unwrap();
+        let _shader_guard = shader_cache.lock().unwrap();
+    }
+    {
+        let _pipeline_guard = pipeline.lock().unwrap();
+        let _texture_guard = texture_cache.lock().unwrap();
+    }
+}
+
+ordered_mutex::define_rank! {
+    static CYCLIC_RANK;
+
+    #[repr(u32)]
+    #[derive(Clone, PartialEq)]
+    enum CyclicRank {
+        First => { Second },
+        Second => { First },
+    }
+}
+
+#[test]
+#[should_panic]
+fn cyclic_ranking_panics_on_first_use() {
+    let first: Mutex<(), CyclicRank> = Mutex::new((), CyclicRank::First, "first");
+    let _guard = first.lock().unwrap();
+}
+
+ordered_mutex::define_rank! {
+    static SELF_LOOP_RANK;
+
+    #[repr(u32)]
+    #[derive(Clone, PartialEq)]
+    enum SelfLoopRank {
+        Shared => { Shared },
+    }
+}
+
+// A rank listing itself as a follower used to be treated as fine, not
+// a cycle, which let a thread acquire any second, unrelated lock of
+// that rank -- the AB-BA deadlock this crate exists to rule out. It's
+// now rejected as a (trivial) cycle instead.
+#[test]
+#[should_panic]
+fn self_loop_ranking_panics_as_a_cycle() {
+    let shared: Mutex<(), SelfLoopRank> = Mutex::new((), SelfLoopRank::Shared, "shared");
+    let _guard = shared.lock().unwrap();
+}